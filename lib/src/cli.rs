@@ -115,11 +115,18 @@ pub(crate) enum ContainerOpts {
         quiet: bool,
     },
 
+    #[clap(alias = "inspect")]
     /// Print information about an exported ostree-container image.
+    ///
+    /// Only the manifest and configuration are fetched; no layers are downloaded.
     Info {
         /// Image reference, e.g. registry:quay.io/exampleos/exampleos:latest
         #[clap(value_parser = parse_imgref)]
         imgref: OstreeImageReference,
+
+        /// Output in JSON format
+        #[clap(long)]
+        json: bool,
     },
 
     /// Wrap an ostree commit into a container image.
@@ -684,7 +691,10 @@ async fn container_import(
         pb.set_message("Downloading...");
         pb
     });
-    let importer = ImageImporter::new(repo, imgref, proxyopts.into()).await?;
+    let mut importer = ImageImporter::new(repo, imgref, proxyopts.into()).await?;
+    if let Some(write_ref) = write_ref {
+        importer.set_write_ref(write_ref);
+    }
     let import = importer.unencapsulate().await;
     // Ensure we finish the progress bar before potentially propagating an error
     if let Some(pb) = pb.as_ref() {
@@ -695,12 +705,6 @@ async fn container_import(
         print_deprecated_warning(warning).await;
     }
     if let Some(write_ref) = write_ref {
-        repo.set_ref_immediate(
-            None,
-            write_ref,
-            Some(import.ostree_commit.as_str()),
-            gio::Cancellable::NONE,
-        )?;
         println!(
             "Imported: {} => {}",
             write_ref,
@@ -839,10 +843,60 @@ async fn container_export(
     Ok(())
 }
 
+/// Summary of a container image with an encapsulated ostree commit.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct ContainerInfo<'a> {
+    /// The manifest digest
+    digest: String,
+    /// The ostree commit, from the `ostree.commit` label
+    ostree_commit: Option<&'a str>,
+    /// The image version, if any
+    version: Option<&'a str>,
+    /// Number of layers
+    n_layers: usize,
+    /// Total compressed size of all layers
+    size: u64,
+    /// All `ostree.` prefixed labels
+    labels: BTreeMap<&'a str, &'a str>,
+}
+
 /// Load metadata for a container image with an encapsulated ostree commit.
-async fn container_info(imgref: &OstreeImageReference) -> Result<()> {
-    let (_, digest) = crate::container::fetch_manifest(imgref).await?;
-    println!("{} digest: {}", imgref, digest);
+async fn container_info(imgref: &OstreeImageReference, json: bool) -> Result<()> {
+    let (manifest, digest, config) = crate::container::fetch_manifest_and_config(imgref).await?;
+    let labels = ostree_container::labels_of(&config)
+        .into_iter()
+        .flatten()
+        .filter(|(k, _)| k.starts_with("ostree."))
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect::<BTreeMap<_, _>>();
+    let info = ContainerInfo {
+        digest: digest.to_string(),
+        ostree_commit: labels.get(ostree_container::OSTREE_COMMIT_LABEL).copied(),
+        version: ostree_container::version_for_config(&config),
+        n_layers: manifest.layers().len(),
+        size: manifest.layers().iter().map(|l| l.size()).sum(),
+        labels,
+    };
+    if json {
+        let stdout = std::io::stdout().lock();
+        let mut stdout = std::io::BufWriter::new(stdout);
+        serde_json::to_writer(&mut stdout, &info)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+    println!("{} digest: {}", imgref, info.digest);
+    if let Some(commit) = info.ostree_commit {
+        println!("ostree commit: {commit}");
+    }
+    if let Some(version) = info.version {
+        println!("version: {version}");
+    }
+    let size = glib::format_size(info.size);
+    println!("layers: {} ({size})", info.n_layers);
+    for (k, v) in info.labels {
+        println!("label: {k}={v}");
+    }
     Ok(())
 }
 
@@ -1039,7 +1093,7 @@ async fn run_from_opt(opt: Opt) -> Result<()> {
         Opt::Tar(TarOpts::Import(ref opt)) => tar_import(opt).await,
         Opt::Tar(TarOpts::Export(ref opt)) => tar_export(opt),
         Opt::Container(o) => match o {
-            ContainerOpts::Info { imgref } => container_info(&imgref).await,
+            ContainerOpts::Info { imgref, json } => container_info(&imgref, json).await,
             ContainerOpts::Commit {} => container_commit().await,
             ContainerOpts::Unencapsulate {
                 repo,
@@ -174,6 +174,8 @@ pub struct ImageImporter {
     require_bootable: bool,
     /// If true, we have ostree v2024.3 or newer.
     ostree_v2024_3: bool,
+    /// When unencapsulating, write this ref pointing to the imported commit
+    write_ref: Option<String>,
     pub(crate) proxy_img: OpenedImage,
 
     layer_progress: Option<Sender<ImportProgress>>,
@@ -294,6 +296,40 @@ impl PreparedImport {
     }
 }
 
+/// A layer which was checked by [`ImageImporter::verify`].
+#[derive(Debug)]
+pub struct VerifiedLayer {
+    /// The layer descriptor.
+    pub layer: oci_image::Descriptor,
+    /// Number of entries in the layer tarball.
+    pub n_entries: u64,
+}
+
+/// Result of invoking [`ImageImporter::verify`].
+#[derive(Debug)]
+pub struct VerifiedImage {
+    /// The manifest digest
+    pub manifest_digest: Digest,
+    /// The deserialized manifest.
+    pub manifest: oci_image::ImageManifest,
+    /// The deserialized configuration.
+    pub config: oci_image::ImageConfiguration,
+    /// All layers, in the same order as [`PreparedImport::all_layers`].
+    pub layers: Vec<VerifiedLayer>,
+}
+
+impl VerifiedImage {
+    /// Retrieve the container image version.
+    pub fn version(&self) -> Option<&str> {
+        super::version_for_config(&self.config)
+    }
+
+    /// Total number of tar entries across all layers.
+    pub fn n_entries(&self) -> u64 {
+        self.layers.iter().map(|l| l.n_entries).sum()
+    }
+}
+
 // Given a manifest, compute its ostree ref name and cached ostree commit
 pub(crate) fn query_layer(
     repo: &ostree::Repo,
@@ -419,6 +455,47 @@ pub(crate) fn parse_manifest_layout<'a>(
     Ok((ostree_layer, chunk_layers, derived_layers))
 }
 
+/// Read every entry of a tar stream (including its content) and return the entry count.
+/// The archive must be terminated by an end-of-archive marker, followed only by zero padding.
+/// The stream is read to the end, so that the decompressor also validates it.
+fn verify_tar_stream(
+    mut archive: tar::Archive<impl std::io::Read>,
+    cancellable: Option<&gio::Cancellable>,
+) -> Result<u64> {
+    use std::io::Read;
+    let mut n_entries = 0u64;
+    for entry in archive.entries()? {
+        if let Some(c) = cancellable {
+            c.set_error_if_cancelled()?;
+        }
+        let mut entry = entry?;
+        let _ = entry.path()?;
+        std::io::copy(&mut entry, &mut std::io::sink())?;
+        n_entries += 1;
+    }
+    // The tar crate stops at the first zero block of the end-of-archive marker (or at EOF
+    // on a block boundary); the second zero block must still be there.
+    let mut rest = archive.into_inner();
+    let mut buf = [0u8; 8192];
+    let mut trailer_len = 0u64;
+    loop {
+        let n = match rest.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if buf[..n].iter().any(|&b| b != 0) {
+            anyhow::bail!("Unexpected data after end of archive");
+        }
+        trailer_len += n as u64;
+    }
+    if trailer_len < 512 {
+        anyhow::bail!("Missing end of archive marker");
+    }
+    Ok(n_entries)
+}
+
 /// Find the timestamp of the manifest (or config), ignoring errors.
 fn timestamp_of_manifest_or_config(
     manifest: &ImageManifest,
@@ -481,6 +558,7 @@ impl ImageImporter {
             ostree_v2024_3: ostree::check_version(2024, 3),
             disable_gc: false,
             require_bootable: false,
+            write_ref: None,
             imgref: imgref.clone(),
             layer_progress: None,
             layer_byte_progress: None,
@@ -499,6 +577,14 @@ impl ImageImporter {
         self.no_imgref = true;
     }
 
+    /// When using [`Self::unencapsulate`], also write the provided ref pointing to the
+    /// imported commit.  The ref is written in the same transaction as the commit.
+    ///
+    /// This is not supported by [`Self::import`] or [`Self::verify`], which will error out.
+    pub fn set_write_ref(&mut self, name: &str) {
+        self.write_ref = Some(name.to_owned());
+    }
+
     /// Require that the image has the bootable metadata field
     pub fn require_bootable(&mut self) {
         self.require_bootable = true;
@@ -626,19 +712,24 @@ impl ImageImporter {
         Ok(Box::new(imp))
     }
 
+    /// If we are deferring to `containers-policy.json`, verify that it does not accept anything.
+    fn check_container_policy(&self) -> Result<()> {
+        if matches!(self.imgref.sigverify, SignatureSource::ContainerPolicy)
+            && skopeo::container_policy_is_default_insecure()?
+        {
+            return Err(anyhow!("containers-policy.json specifies a default of `insecureAcceptAnything`; refusing usage"));
+        }
+        Ok(())
+    }
+
     /// Determine if there is a new manifest, and if so return its digest.
     #[context("Fetching manifest")]
     pub(crate) async fn prepare_internal(&mut self, verify_layers: bool) -> Result<PrepareResult> {
-        match &self.imgref.sigverify {
-            SignatureSource::ContainerPolicy if skopeo::container_policy_is_default_insecure()? => {
-                return Err(anyhow!("containers-policy.json specifies a default of `insecureAcceptAnything`; refusing usage"));
-            }
-            SignatureSource::OstreeRemote(_) if verify_layers => {
-                return Err(anyhow!(
-                    "Cannot currently verify layered containers via ostree remote"
-                ));
-            }
-            _ => {}
+        self.check_container_policy()?;
+        if verify_layers && matches!(self.imgref.sigverify, SignatureSource::OstreeRemote(_)) {
+            return Err(anyhow!(
+                "Cannot currently verify layered containers via ostree remote"
+            ));
         }
 
         let (manifest_digest, manifest) = self.proxy.fetch_manifest(&self.proxy_img).await?;
@@ -688,19 +779,59 @@ impl ImageImporter {
         Ok(PrepareResult::Ready(imp))
     }
 
-    /// Extract the base ostree commit.
+    /// Fetch a layer, and pass its decompressed tar stream to `f` in a blocking worker.
+    /// Once `f` returns, the remainder of the stream is drained.  Returns the result of `f`
+    /// and the number of (compressed) bytes read from the blob.
+    async fn fetch_and_process_layer<T, F>(
+        &self,
+        manifest: &ImageManifest,
+        layer: &Descriptor,
+        des_layers: Option<&Vec<containers_image_proxy::ConvertedLayerInfo>>,
+        f: F,
+    ) -> Result<(T, u64)>
+    where
+        T: std::fmt::Debug + Send + 'static,
+        F: FnOnce(tar::Archive<&mut Box<dyn std::io::Read + Send>>, &gio::Cancellable) -> Result<T>
+            + Send
+            + 'static,
+    {
+        let (blob, driver, media_type) = fetch_layer(
+            &self.proxy,
+            &self.proxy_img,
+            manifest,
+            layer,
+            self.layer_byte_progress.as_ref(),
+            des_layers,
+            self.imgref.imgref.transport,
+        )
+        .await?;
+        let (blob, fetched) = super::unencapsulate::ProgressReader::new(blob);
+        let task = crate::tokio_util::spawn_blocking_cancellable_flatten(move |cancellable| {
+            let blob = tokio_util::io::SyncIoBridge::new(blob);
+            let mut blob = super::unencapsulate::decompressor(&media_type, blob)?;
+            let r = f(tar::Archive::new(&mut blob), cancellable)?;
+            std::io::copy(&mut blob, &mut std::io::sink())?;
+            Ok(r)
+        })
+        .map_err(|e| e.context(format!("Layer {}", layer.digest())));
+        let r = super::unencapsulate::join_fetch(task, driver).await?;
+        let fetched = *fetched.borrow();
+        Ok((r, fetched))
+    }
+
+    /// Extract the base ostree commit.  If `commit_ref` is provided, it will be
+    /// written pointing to the commit as part of the import transaction.
+    ///
+    /// Returns the number of bytes fetched.
     #[context("Unencapsulating base")]
     pub(crate) async fn unencapsulate_base(
         &mut self,
         import: &mut store::PreparedImport,
         write_refs: bool,
-    ) -> Result<()> {
+        commit_ref: Option<&str>,
+    ) -> Result<u64> {
         tracing::debug!("Fetching base");
-        if matches!(self.imgref.sigverify, SignatureSource::ContainerPolicy)
-            && skopeo::container_policy_is_default_insecure()?
-        {
-            return Err(anyhow!("containers-policy.json specifies a default of `insecureAcceptAnything`; refusing usage"));
-        }
+        self.check_container_policy()?;
         let remote = match &self.imgref.sigverify {
             SignatureSource::OstreeRemote(remote) => Some(remote.clone()),
             SignatureSource::ContainerPolicy | SignatureSource::ContainerPolicyAllowInsecure => {
@@ -708,6 +839,7 @@ impl ImageImporter {
             }
         };
         let des_layers = self.proxy.get_layer_info(&self.proxy_img).await?;
+        let mut fetched_size = 0u64;
         for layer in import.ostree_layers.iter_mut() {
             if layer.commit.is_some() {
                 continue;
@@ -716,39 +848,31 @@ impl ImageImporter {
                 p.send(ImportProgress::OstreeChunkStarted(layer.layer.clone()))
                     .await?;
             }
-            let (blob, driver, media_type) = fetch_layer(
-                &self.proxy,
-                &self.proxy_img,
-                &import.manifest,
-                &layer.layer,
-                self.layer_byte_progress.as_ref(),
-                des_layers.as_ref(),
-                self.imgref.imgref.transport,
-            )
-            .await?;
             let repo = self.repo.clone();
             let target_ref = layer.ostree_ref.clone();
-            let import_task =
-                crate::tokio_util::spawn_blocking_cancellable_flatten(move |cancellable| {
-                    let txn = repo.auto_transaction(Some(cancellable))?;
-                    let mut importer = crate::tar::Importer::new_for_object_set(&repo);
-                    let blob = tokio_util::io::SyncIoBridge::new(blob);
-                    let blob = super::unencapsulate::decompressor(&media_type, blob)?;
-                    let mut archive = tar::Archive::new(blob);
-                    importer.import_objects(&mut archive, Some(cancellable))?;
-                    let commit = if write_refs {
-                        let commit = importer.finish_import_object_set()?;
-                        repo.transaction_set_ref(None, &target_ref, Some(commit.as_str()));
-                        tracing::debug!("Wrote {} => {}", target_ref, commit);
-                        Some(commit)
-                    } else {
-                        None
-                    };
-                    txn.commit(Some(cancellable))?;
-                    Ok::<_, anyhow::Error>(commit)
-                })
-                .map_err(|e| e.context(format!("Layer {}", layer.layer.digest())));
-            let commit = super::unencapsulate::join_fetch(import_task, driver).await?;
+            let (commit, fetched) = self
+                .fetch_and_process_layer(
+                    &import.manifest,
+                    &layer.layer,
+                    des_layers.as_ref(),
+                    move |mut archive, cancellable| {
+                        let txn = repo.auto_transaction(Some(cancellable))?;
+                        let mut importer = crate::tar::Importer::new_for_object_set(&repo);
+                        importer.import_objects(&mut archive, Some(cancellable))?;
+                        let commit = if write_refs {
+                            let commit = importer.finish_import_object_set()?;
+                            repo.transaction_set_ref(None, &target_ref, Some(commit.as_str()));
+                            tracing::debug!("Wrote {} => {}", target_ref, commit);
+                            Some(commit)
+                        } else {
+                            None
+                        };
+                        txn.commit(Some(cancellable))?;
+                        Ok(commit)
+                    },
+                )
+                .await?;
+            fetched_size += fetched;
             layer.commit = commit;
             if let Some(p) = self.layer_progress.as_ref() {
                 p.send(ImportProgress::OstreeChunkCompleted(layer.layer.clone()))
@@ -762,36 +886,34 @@ impl ImageImporter {
                 ))
                 .await?;
             }
-            let (blob, driver, media_type) = fetch_layer(
-                &self.proxy,
-                &self.proxy_img,
-                &import.manifest,
-                &import.ostree_commit_layer.layer,
-                self.layer_byte_progress.as_ref(),
-                des_layers.as_ref(),
-                self.imgref.imgref.transport,
-            )
-            .await?;
             let repo = self.repo.clone();
             let target_ref = import.ostree_commit_layer.ostree_ref.clone();
-            let import_task =
-                crate::tokio_util::spawn_blocking_cancellable_flatten(move |cancellable| {
-                    let txn = repo.auto_transaction(Some(cancellable))?;
-                    let mut importer = crate::tar::Importer::new_for_commit(&repo, remote);
-                    let blob = tokio_util::io::SyncIoBridge::new(blob);
-                    let blob = super::unencapsulate::decompressor(&media_type, blob)?;
-                    let mut archive = tar::Archive::new(blob);
-                    importer.import_commit(&mut archive, Some(cancellable))?;
-                    let commit = importer.finish_import_commit();
-                    if write_refs {
-                        repo.transaction_set_ref(None, &target_ref, Some(commit.as_str()));
-                        tracing::debug!("Wrote {} => {}", target_ref, commit);
-                    }
-                    repo.mark_commit_partial(&commit, false)?;
-                    txn.commit(Some(cancellable))?;
-                    Ok::<_, anyhow::Error>(commit)
-                });
-            let commit = super::unencapsulate::join_fetch(import_task, driver).await?;
+            let commit_ref = commit_ref.map(ToOwned::to_owned);
+            let (commit, fetched) = self
+                .fetch_and_process_layer(
+                    &import.manifest,
+                    &import.ostree_commit_layer.layer,
+                    des_layers.as_ref(),
+                    move |mut archive, cancellable| {
+                        let txn = repo.auto_transaction(Some(cancellable))?;
+                        let mut importer = crate::tar::Importer::new_for_commit(&repo, remote);
+                        importer.import_commit(&mut archive, Some(cancellable))?;
+                        let commit = importer.finish_import_commit();
+                        if write_refs {
+                            repo.transaction_set_ref(None, &target_ref, Some(commit.as_str()));
+                            tracing::debug!("Wrote {} => {}", target_ref, commit);
+                        }
+                        if let Some(commit_ref) = commit_ref.as_deref() {
+                            repo.transaction_set_ref(None, commit_ref, Some(commit.as_str()));
+                            tracing::debug!("Wrote {} => {}", commit_ref, commit);
+                        }
+                        repo.mark_commit_partial(&commit, false)?;
+                        txn.commit(Some(cancellable))?;
+                        Ok(commit)
+                    },
+                )
+                .await?;
+            fetched_size += fetched;
             import.ostree_commit_layer.commit = Some(commit);
             if let Some(p) = self.layer_progress.as_ref() {
                 p.send(ImportProgress::OstreeChunkCompleted(
@@ -799,26 +921,44 @@ impl ImageImporter {
                 ))
                 .await?;
             }
+        } else if let Some(commit_ref) = commit_ref {
+            // The commit is already present and referenced by its layer ref,
+            // so it's safe to write this outside of a transaction.
+            let commit = import.ostree_commit_layer.commit.as_deref();
+            self.repo
+                .set_ref_immediate(None, commit_ref, commit, gio::Cancellable::NONE)?;
         };
-        Ok(())
+        Ok(fetched_size)
     }
 
     /// Retrieve an inner ostree commit.
     ///
     /// This does not write cached references for each blob, and errors out if
-    /// the image has any non-ostree layers.
+    /// the image has any non-ostree layers.  See also [`Self::set_write_ref`].
     pub async fn unencapsulate(mut self) -> Result<Import> {
+        let start = std::time::Instant::now();
         let mut prep = match self.prepare_internal(false).await? {
             PrepareResult::AlreadyPresent(_) => {
                 panic!("Should not have image present for unencapsulation")
             }
             PrepareResult::Ready(r) => r,
         };
+        let manifest_duration = start.elapsed();
         if !prep.layers.is_empty() {
             anyhow::bail!("Image has {} non-ostree layers", prep.layers.len());
         }
         let deprecated_warning = prep.deprecated_warning().map(ToOwned::to_owned);
-        self.unencapsulate_base(&mut prep, false).await?;
+        let n_layers_fetched = prep.all_layers().filter(|l| l.commit.is_none()).count() as u32;
+        let write_ref = self.write_ref.take();
+        let ref_updated = write_ref
+            .as_deref()
+            .map(|r| self.repo.resolve_rev(r, true).map(|v| v.is_some()))
+            .transpose()?;
+        let start = std::time::Instant::now();
+        let fetched_size = self
+            .unencapsulate_base(&mut prep, false, write_ref.as_deref())
+            .await?;
+        let layers_duration = start.elapsed();
         // TODO change the imageproxy API to ensure this happens automatically when
         // the image reference is dropped
         self.proxy.close_image(&self.proxy_img).await?;
@@ -828,6 +968,56 @@ impl ImageImporter {
             ostree_commit,
             image_digest,
             deprecated_warning,
+            ref_updated,
+            stats: ImportStats {
+                n_layers_fetched,
+                fetched_size,
+                manifest_duration,
+                layers_duration,
+            },
+        })
+    }
+
+    /// Fetch the manifest, configuration and all layers of the image and check that
+    /// they can be imported, without writing anything to the repository.
+    ///
+    /// Each layer is decompressed and its tarball is read to the end, but not
+    /// parsed as ostree content.  In particular, signatures from an ostree
+    /// remote (see [`SignatureSource::OstreeRemote`]) are not checked.
+    #[context("Verifying")]
+    pub async fn verify(mut self) -> Result<VerifiedImage> {
+        if self.write_ref.is_some() {
+            anyhow::bail!("Writing a ref is only supported when unencapsulating");
+        }
+        self.check_container_policy()?;
+        let (manifest_digest, manifest) = self.proxy.fetch_manifest(&self.proxy_img).await?;
+        let manifest_digest = Digest::from_str(&manifest_digest)?;
+        let config = self.proxy.fetch_config(&self.proxy_img).await?;
+        let prep = self.create_prepared_import(manifest_digest, manifest, config, None, None)?;
+        let des_layers = self.proxy.get_layer_info(&self.proxy_img).await?;
+        let mut layers = Vec::new();
+        for layer in prep.all_layers() {
+            let (n_entries, _) = self
+                .fetch_and_process_layer(
+                    &prep.manifest,
+                    layer.layer(),
+                    des_layers.as_ref(),
+                    |archive, cancellable| verify_tar_stream(archive, Some(cancellable)),
+                )
+                .await?;
+            tracing::debug!("Verified {}: {} entries", layer.layer().digest(), n_entries);
+            layers.push(VerifiedLayer {
+                layer: layer.layer().clone(),
+                n_entries,
+            });
+        }
+        self.proxy.close_image(&self.proxy_img).await?;
+        let prep = *prep;
+        Ok(VerifiedImage {
+            manifest_digest: prep.manifest_digest,
+            manifest: prep.manifest,
+            config: prep.config,
+            layers,
         })
     }
 
@@ -839,12 +1029,15 @@ impl ImageImporter {
         mut self,
         mut import: Box<PreparedImport>,
     ) -> Result<Box<LayeredImageState>> {
+        if self.write_ref.is_some() {
+            anyhow::bail!("Writing a ref is only supported when unencapsulating");
+        }
         if let Some(status) = import.format_layer_status() {
             system_repo_journal_print(&self.repo, libsystemd::logging::Priority::Info, &status);
         }
         // First download all layers for the base image (if necessary) - we need the SELinux policy
         // there to label all following layers.
-        self.unencapsulate_base(&mut import, true).await?;
+        self.unencapsulate_base(&mut import, true, None).await?;
         let des_layers = self.proxy.get_layer_info(&self.proxy_img).await?;
         let proxy = self.proxy;
         let proxy_img = self.proxy_img;
@@ -1844,6 +2037,63 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_verify_tar_stream() -> Result<()> {
+        let mut b = tar::Builder::new(Vec::new());
+        for (path, data) in [("usr/bin/foo", b"foo".as_slice()), ("usr/bin/bar", b"bar")] {
+            let mut h = tar::Header::new_gnu();
+            h.set_mode(0o755);
+            h.set_size(data.len() as u64);
+            b.append_data(&mut h, path, data)?;
+        }
+        let buf = b.into_inner()?;
+        let gz = {
+            use std::io::Write;
+            let mut w = flate2::write::GzEncoder::new(Vec::new(), Compression::fast());
+            w.write_all(&buf)?;
+            w.finish()?
+        };
+        let decompress =
+            |src: Vec<u8>| decompressor(&MediaType::ImageLayerGzip, std::io::Cursor::new(src));
+
+        let r = verify_tar_stream(tar::Archive::new(decompress(gz.clone())?), None)?;
+        assert_eq!(r, 2);
+        let r = verify_tar_stream(tar::Archive::new(std::io::Cursor::new(buf.clone())), None)?;
+        assert_eq!(r, 2);
+
+        // A truncated stream is an error
+        let truncated = gz[..gz.len() - 4].to_vec();
+        assert!(verify_tar_stream(tar::Archive::new(decompress(truncated)?), None).is_err());
+        let verify_plain = |src: &[u8]| {
+            verify_tar_stream(tar::Archive::new(std::io::Cursor::new(src.to_vec())), None)
+        };
+        assert!(verify_plain(&buf[..700]).is_err());
+        // Truncated after a complete entry
+        let e = verify_plain(&buf[..1024]).unwrap_err();
+        assert_eq!(e.to_string(), "Missing end of archive marker");
+        // Truncated in the middle of the end-of-archive marker
+        let e = verify_plain(&buf[..buf.len() - 512]).unwrap_err();
+        assert_eq!(e.to_string(), "Missing end of archive marker");
+        // Zero padding after the marker is fine, anything else is not
+        let mut padded = buf.clone();
+        padded.extend_from_slice(&[0u8; 10240]);
+        assert_eq!(verify_plain(&padded)?, 2);
+        let mut junk = buf.clone();
+        junk.extend_from_slice(b"junk");
+        let e = verify_plain(&junk).unwrap_err();
+        assert_eq!(e.to_string(), "Unexpected data after end of archive");
+
+        // A cancelled stream is also an error
+        let cancellable = gio::Cancellable::new();
+        cancellable.cancel();
+        let r = verify_tar_stream(
+            tar::Archive::new(std::io::Cursor::new(buf)),
+            Some(&cancellable),
+        );
+        assert!(r.is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_ref_for_descriptor() {
         let d = DescriptorBuilder::default()
@@ -1859,4 +2109,52 @@ mod tests {
             .unwrap();
         assert_eq!(ref_for_layer(&d).unwrap(), "ostree/container/blob/sha256_3A_2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae");
     }
+
+    #[test]
+    fn test_parse_manifest_layout_errors() {
+        let digest = Sha256Digest::from_str(
+            "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae",
+        )
+        .unwrap();
+        let descriptor = |media_type| {
+            DescriptorBuilder::default()
+                .size(42u64)
+                .media_type(media_type)
+                .digest(digest.clone())
+                .build()
+                .unwrap()
+        };
+        let manifest = |layers| {
+            oci_image::ImageManifestBuilder::default()
+                .schema_version(2u32)
+                .config(descriptor(MediaType::ImageConfig))
+                .layers(layers)
+                .build()
+                .unwrap()
+        };
+        let mut config = ImageConfiguration::default();
+
+        let empty = manifest(Vec::new());
+        let e = parse_manifest_layout(&empty, &config).err().unwrap();
+        assert_eq!(e.root_cause().to_string(), "No layers in manifest");
+
+        let one_layer = manifest(vec![descriptor(MediaType::ImageLayerGzip)]);
+        let e = parse_manifest_layout(&one_layer, &config).err().unwrap();
+        assert_eq!(
+            e.root_cause().to_string(),
+            "No ostree.final-diffid label found, not an ostree encapsulated container"
+        );
+
+        let labels = HashMap::from([(DIFFID_LABEL.to_owned(), "sha256:1234".to_owned())]);
+        let c = oci_image::ConfigBuilder::default()
+            .labels(labels)
+            .build()
+            .unwrap();
+        config.set_config(Some(c));
+        let e = parse_manifest_layout(&one_layer, &config).err().unwrap();
+        assert_eq!(
+            e.root_cause().to_string(),
+            "Missing ostree.final-diffid sha256:1234"
+        );
+    }
 }
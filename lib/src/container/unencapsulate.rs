@@ -65,7 +65,7 @@ pub(crate) struct ProgressReader<T> {
 
 impl<T: AsyncRead> ProgressReader<T> {
     pub(crate) fn new(reader: T) -> (Self, Receiver<u64>) {
-        let (progress, r) = tokio::sync::watch::channel(1);
+        let (progress, r) = tokio::sync::watch::channel(0);
         let progress = Arc::new(Mutex::new(progress));
         (ProgressReader { reader, progress }, r)
     }
@@ -132,6 +132,7 @@ pub async fn fetch_manifest_and_config(
     let (digest, manifest) = proxy.fetch_manifest(oi).await?;
     let digest = oci_image::Digest::from_str(&digest)?;
     let config = proxy.fetch_config(oi).await?;
+    proxy.close_image(oi).await?;
     Ok((manifest, digest, config))
 }
 
@@ -145,6 +146,30 @@ pub struct Import {
 
     /// Any deprecation warning
     pub deprecated_warning: Option<String>,
+
+    /// If a ref was requested via [`super::store::ImageImporter::set_write_ref`], this is
+    /// `Some(true)` if it already existed and was updated, or `Some(false)` if it was created.
+    pub ref_updated: Option<bool>,
+
+    /// Statistics about the fetch
+    pub stats: ImportStats,
+}
+
+/// Statistics about an import operation
+#[derive(Debug, Default, Clone)]
+pub struct ImportStats {
+    /// Number of layers which were fetched, i.e. not already present in the repository
+    pub n_layers_fetched: u32,
+    /// Total number of bytes read from the fetched layer blobs.  These are normally
+    /// compressed, and match the sizes in the manifest.  When fetching from
+    /// `containers-storage`, the blobs are generated by the proxy and may differ
+    /// from the manifest; this case is not covered by the test suite.
+    pub fetched_size: u64,
+    /// Time spent fetching the manifest and configuration
+    pub manifest_duration: std::time::Duration,
+    /// Time spent fetching and importing layers.  Note these two happen concurrently
+    /// as the layers are streamed into the repository.
+    pub layers_duration: std::time::Duration,
 }
 
 /// Use this to process potential errors from a worker and a driver.
@@ -162,6 +187,10 @@ pub struct Import {
 /// to see if the worker function had an error *and* if the proxy
 /// had an error, but if the proxy's error ends in `broken pipe`
 /// then it means the real only error is from the worker.
+///
+/// Errors are annotated with the stage that failed: fetching the blob
+/// (the proxy side), or processing (decompressing and parsing) the layer
+/// on our side.
 pub(crate) async fn join_fetch<T: std::fmt::Debug>(
     worker: impl Future<Output = Result<T>>,
     driver: impl Future<Output = Result<()>>,
@@ -173,13 +202,15 @@ pub(crate) async fn join_fetch<T: std::fmt::Debug>(
             let text = driver.root_cause().to_string();
             if text.ends_with("broken pipe") {
                 tracing::trace!("Ignoring broken pipe failure from driver");
-                Err(worker)
+                Err(worker.context("Processing layer"))
             } else {
-                Err(worker.context(format!("proxy failure: {} and client error", text)))
+                Err(worker.context(format!(
+                    "Fetching blob failed ({text}) and processing layer failed"
+                )))
             }
         }
-        (Ok(_), Err(driver)) => Err(driver),
-        (Err(worker), Ok(())) => Err(worker),
+        (Ok(_), Err(driver)) => Err(driver.context("Fetching blob")),
+        (Err(worker), Ok(())) => Err(worker.context("Processing layer")),
     }
 }
 
@@ -274,3 +305,83 @@ pub(crate) async fn fetch_layer<'a>(
         Ok((Box::new(blob), Either::Right(driver), media_type))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_decompressor() -> Result<()> {
+        let data = b"some layer content";
+        let gz = {
+            let mut w = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+            w.write_all(data)?;
+            w.finish()?
+        };
+        let zst = zstd::stream::encode_all(&data[..], 0)?;
+        for (media_type, src) in [
+            (oci_image::MediaType::ImageLayerGzip, gz),
+            (oci_image::MediaType::ImageLayerZstd, zst),
+            (oci_image::MediaType::ImageLayer, data.to_vec()),
+            (
+                oci_image::MediaType::Other(DOCKER_TYPE_LAYER_TAR.into()),
+                data.to_vec(),
+            ),
+        ] {
+            let mut buf = Vec::new();
+            decompressor(&media_type, std::io::Cursor::new(src))?.read_to_end(&mut buf)?;
+            assert_eq!(buf, data, "decompressing {media_type}");
+        }
+
+        let e = decompressor(&oci_image::MediaType::ImageManifest, std::io::empty())
+            .err()
+            .unwrap();
+        assert_eq!(
+            e.to_string(),
+            "Unhandled layer type: application/vnd.oci.image.manifest.v1+json"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_join_fetch() {
+        let r = join_fetch(async { Ok(42) }, async { Ok(()) }).await;
+        assert_eq!(r.unwrap(), 42);
+
+        // A failure on the proxy side should be identified as a fetch error
+        let r = join_fetch(async { Ok(42) }, async { Err(anyhow!("connection reset")) }).await;
+        assert_eq!(
+            format!("{:#}", r.unwrap_err()),
+            "Fetching blob: connection reset"
+        );
+
+        let r = join_fetch(async { Err::<(), _>(anyhow!("invalid tar")) }, async {
+            Ok(())
+        })
+        .await;
+        assert_eq!(
+            format!("{:#}", r.unwrap_err()),
+            "Processing layer: invalid tar"
+        );
+
+        // If we closed the pipe, only our error is relevant
+        let r = join_fetch(async { Err::<(), _>(anyhow!("invalid tar")) }, async {
+            Err(anyhow!("write: broken pipe"))
+        })
+        .await;
+        assert_eq!(
+            format!("{:#}", r.unwrap_err()),
+            "Processing layer: invalid tar"
+        );
+
+        let r = join_fetch(async { Err::<(), _>(anyhow!("invalid tar")) }, async {
+            Err(anyhow!("connection reset"))
+        })
+        .await;
+        assert_eq!(
+            format!("{:#}", r.unwrap_err()),
+            "Fetching blob failed (connection reset) and processing layer failed: invalid tar"
+        );
+    }
+}
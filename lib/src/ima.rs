@@ -39,15 +39,29 @@ pub struct ImaOpts {
 
 /// Convert a GVariant of type `a(ayay)` to a mutable map
 fn xattrs_to_map(v: &glib::Variant) -> BTreeMap<Vec<u8>, Vec<u8>> {
+    variant_a_ayay_to_vec(v)
+        .expect("xattrs of type a(ayay)")
+        .into_iter()
+        .collect()
+}
+
+/// Decode a GVariant of type `a(ayay)` into owned key/value pairs; this is the inverse
+/// of [`new_variant_a_ayay`].  Returns `None` if the variant has any other type.
+pub(crate) fn variant_a_ayay_to_vec(v: &glib::Variant) -> Option<Vec<(Vec<u8>, Vec<u8>)>> {
+    if v.type_().as_str() != "a(ayay)" {
+        return None;
+    }
     let v = v.data_as_bytes();
-    let v = v.try_as_aligned().unwrap();
+    let v = v.try_as_aligned().ok()?;
     let v = gv!("a(ayay)").cast(v);
-    let mut map: BTreeMap<Vec<u8>, Vec<u8>> = BTreeMap::new();
-    for e in v.iter() {
-        let (k, v) = e.to_tuple();
-        map.insert(k.into(), v.into());
-    }
-    map
+    let r = v
+        .iter()
+        .map(|e| {
+            let (k, v) = e.to_tuple();
+            (k.into(), v.into())
+        })
+        .collect();
+    Some(r)
 }
 
 /// Create a new GVariant of type a(ayay).  This is used by OSTree's extended attributes.
@@ -285,3 +299,39 @@ pub fn ima_sign(repo: &ostree::Repo, ostree_ref: &str, opts: &ImaOpts) -> Result
     let writer = &mut CommitRewriter::new(repo, opts)?;
     writer.map_commit(ostree_ref)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xattrs_roundtrip() {
+        let xattrs = BTreeMap::from([
+            (
+                b"security.selinux".to_vec(),
+                b"system_u:object_r:usr_t:s0".to_vec(),
+            ),
+            (IMA_XATTR.as_bytes().to_vec(), vec![0x03, 0x02, 0x04, 0x00]),
+        ]);
+        let v = new_variant_a_ayay(&xattrs);
+        assert_eq!(v.type_().as_str(), "a(ayay)");
+        assert_eq!(xattrs_to_map(&v), xattrs);
+        let pairs = variant_a_ayay_to_vec(&v).unwrap();
+        assert!(pairs.into_iter().eq(xattrs));
+
+        let empty = new_variant_a_ayay(std::iter::empty::<(&[u8], &[u8])>());
+        assert_eq!(variant_a_ayay_to_vec(&empty).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_variant_a_ayay_wrong_type() {
+        for v in [
+            vec!["foo", "bar"].to_variant(),
+            b"foo".as_slice().to_variant(),
+            (b"foo".as_slice(), b"bar".as_slice()).to_variant(),
+            Variant::array_from_iter::<(String, String)>([("k", "v").to_variant()]),
+        ] {
+            assert!(variant_a_ayay_to_vec(&v).is_none(), "{}", v.type_());
+        }
+    }
+}
@@ -300,7 +300,8 @@ pub(crate) fn filter_tar(
                 continue;
             }
         } else if header.entry_type() == tar::EntryType::Link && is_modified {
-            let target = header
+            // Use the entry API, as the header API does not handle long link names.
+            let target = entry
                 .link_name()?
                 .ok_or_else(|| anyhow!("Invalid empty hardlink"))?;
             let target: &Utf8Path = (&*target).try_into()?;
@@ -588,6 +589,79 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn tar_filter_long_paths() -> Result<()> {
+        use std::io::Read;
+
+        let tempd = tempfile::tempdir()?;
+        let tmpdir = Dir::open_ambient_dir(tempd.path(), cap_std::ambient_authority())?;
+        let longdir = format!("usr/share/{}", "a".repeat(120));
+        let longfile = format!("{longdir}/file");
+        let longlink = format!("{longdir}/link");
+        let sysroot_obj = format!(
+            "{}objects/ab/{}.file",
+            crate::tar::REPO_PREFIX,
+            "c".repeat(80)
+        );
+        let xattr_key = "SCHILY.xattr.user.foo";
+
+        let mut src = tar::Builder::new(Vec::new());
+        let mut h = tar::Header::new_gnu();
+        h.set_entry_type(tar::EntryType::Regular);
+        h.set_mode(0o644);
+        h.set_size(5);
+        src.append_pax_extensions([(xattr_key, &b"bar"[..])])?;
+        src.append_data(&mut h.clone(), &longfile, &b"hello"[..])?;
+        // A modified object in the repo, and a hardlink to it with a long target
+        h.set_mtime(1);
+        src.append_data(&mut h.clone(), &sysroot_obj, &b"world"[..])?;
+        h.set_entry_type(tar::EntryType::Link);
+        h.set_size(0);
+        src.append_link(&mut h.clone(), "usr/bin/foo", &sysroot_obj)?;
+        h.set_mtime(0);
+        src.append_link(&mut h.clone(), &longlink, &longfile)?;
+        let src = src.into_inner()?;
+
+        let mut dest = Vec::new();
+        let filtered = filter_tar(src.as_slice(), &mut dest, &Default::default(), &tmpdir)?;
+        assert!(filtered.is_empty());
+
+        let mut dest = tar::Archive::new(dest.as_slice());
+        let mut n_entries = 0;
+        for entry in dest.entries()? {
+            let mut entry = entry?;
+            n_entries += 1;
+            let path = entry.path()?.to_str().unwrap().to_owned();
+            let path = path.trim_start_matches("./");
+            let entry_type = entry.header().entry_type();
+            if path == longfile {
+                assert_eq!(entry_type, tar::EntryType::Regular);
+                let xattrs = entry
+                    .pax_extensions()?
+                    .unwrap()
+                    .map(|ext| {
+                        let ext = ext?;
+                        Ok((ext.key()?.to_owned(), ext.value_bytes().to_owned()))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                assert_eq!(xattrs, [(xattr_key.to_owned(), b"bar".to_vec())]);
+            } else if path == longlink {
+                assert_eq!(entry_type, tar::EntryType::Link);
+                let target = entry.link_name()?.unwrap();
+                assert_eq!(target.to_str().unwrap(), longfile);
+            } else if path == "usr/bin/foo" {
+                assert_eq!(entry_type, tar::EntryType::Regular);
+                let mut buf = String::new();
+                entry.read_to_string(&mut buf)?;
+                assert_eq!(buf, "world");
+            } else {
+                panic!("Unexpected entry {path}");
+            }
+        }
+        assert_eq!(n_entries, 3);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn tar_filter() -> Result<()> {
         let tempd = tempfile::tempdir()?;
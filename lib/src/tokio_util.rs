@@ -34,11 +34,30 @@ impl Drop for CancelOnDrop {
     }
 }
 
-/// Wrapper for [`tokio::task::spawn_blocking`] which provides a [`gio::Cancellable`] that will be triggered on drop.
+/// Spawn `f` on a blocking thread, passing it a new [`gio::Cancellable`] which is triggered
+/// when `f` returns.  The cancellable is also returned to the caller.
+fn spawn_blocking_with_cancellable<F, R>(f: F) -> (gio::Cancellable, tokio::task::JoinHandle<R>)
+where
+    F: FnOnce(&gio::Cancellable) -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let cancellable = gio::Cancellable::new();
+    let worker = {
+        let cancellable = cancellable.clone();
+        tokio::task::spawn_blocking(move || {
+            let dropper = CancelOnDrop(cancellable);
+            f(&dropper.0)
+        })
+    };
+    (cancellable, worker)
+}
+
+/// Wrapper for [`tokio::task::spawn_blocking`] which provides a [`gio::Cancellable`] that will be triggered when the function returns.
 ///
 /// This function should be used in a Rust/tokio native `async fn`, but that want to invoke
-/// GLib style blocking APIs that use `GCancellable`.  The cancellable will be triggered when this
-/// future is dropped, which helps bound thread usage.
+/// GLib style blocking APIs that use `GCancellable`.  Dropping the returned
+/// [`tokio::task::JoinHandle`] does *not* cancel it; use [`spawn_blocking_cancellable_flatten`]
+/// for that.
 ///
 /// This is in a sense the inverse of [`run_with_cancellable`].
 pub fn spawn_blocking_cancellable<F, R>(f: F) -> tokio::task::JoinHandle<R>
@@ -46,10 +65,7 @@ where
     F: FnOnce(&gio::Cancellable) -> R + Send + 'static,
     R: Send + 'static,
 {
-    tokio::task::spawn_blocking(move || {
-        let dropper = CancelOnDrop(gio::Cancellable::new());
-        f(&dropper.0)
-    })
+    spawn_blocking_with_cancellable(f).1
 }
 
 /// Flatten a nested Result<Result<T>>, defaulting to converting the error type to an `anyhow::Error`.
@@ -64,13 +80,23 @@ where
     }
 }
 
-/// A wrapper around [`spawn_blocking_cancellable`] that flattens nested results.
+/// Like [`spawn_blocking_cancellable`], but flattens nested results.
+///
+/// Additionally, the cancellable will be triggered if the returned future is dropped
+/// before completion (e.g. because it was wrapped in [`tokio::time::timeout`]), which
+/// helps bound thread usage.
 pub fn spawn_blocking_cancellable_flatten<F, T>(f: F) -> impl Future<Output = Result<T>>
 where
     F: FnOnce(&gio::Cancellable) -> Result<T> + Send + 'static,
     T: Send + 'static,
 {
-    spawn_blocking_cancellable(f).map(flatten_anyhow)
+    let (cancellable, worker) = spawn_blocking_with_cancellable(f);
+    let guard = CancelOnDrop(cancellable);
+    async move {
+        let r = worker.await;
+        drop(guard);
+        flatten_anyhow(r)
+    }
 }
 
 /// A wrapper around [`tokio::task::spawn_blocking`] that flattens nested results.
@@ -103,4 +129,20 @@ mod tests {
         let (_, r) = tokio::join!(s, r);
         assert!(r.is_err());
     }
+
+    #[tokio::test]
+    async fn test_spawn_blocking_cancellable_drop() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let worker = spawn_blocking_cancellable_flatten(move |cancellable| {
+            while !cancellable.is_cancelled() {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            tx.send(()).unwrap();
+            Ok(())
+        });
+        let r = tokio::time::timeout(std::time::Duration::from_millis(200), worker).await;
+        assert!(r.is_err());
+        // Dropping the timed out future should have cancelled the worker
+        rx.recv_timeout(std::time::Duration::from_secs(30)).unwrap();
+    }
 }
@@ -551,7 +551,8 @@ async fn impl_test_container_import_export(chunked: bool) -> Result<()> {
         imgref: srcoci_imgref.clone(),
     };
 
-    let (_, pushed_digest) = ostree_ext::container::fetch_manifest(&srcoci_unverified).await?;
+    let (pushed_manifest, pushed_digest) =
+        ostree_ext::container::fetch_manifest(&srcoci_unverified).await?;
     assert_eq!(pushed_digest, digest);
 
     let (_, pushed_digest, _config) =
@@ -589,6 +590,11 @@ async fn impl_test_container_import_export(chunked: bool) -> Result<()> {
         .await
         .context("importing")?;
     assert_eq!(import.ostree_commit, testrev.as_str());
+    let stats = &import.stats;
+    let pushed_layers = pushed_manifest.layers();
+    assert_eq!(stats.n_layers_fetched as usize, pushed_layers.len());
+    let pushed_size = pushed_layers.iter().map(|l| l.size()).sum::<u64>();
+    assert_eq!(stats.fetched_size, pushed_size);
 
     let temp_unsigned = ImageReference {
         transport: Transport::OciDir,
@@ -613,6 +619,104 @@ async fn impl_test_container_import_export(chunked: bool) -> Result<()> {
             .await
             .context("importing")?;
         assert_eq!(import.ostree_commit, testrev.as_str());
+        assert_eq!(import.ref_updated, None);
+    }
+
+    // Write a ref as part of the import
+    {
+        let fixture = Fixture::new_v1()?;
+        let mut imp =
+            store::ImageImporter::new(fixture.destrepo(), &srcoci_unverified, Default::default())
+                .await?;
+        imp.set_write_ref("testref");
+        let import = imp.unencapsulate().await.context("importing")?;
+        assert_eq!(import.ostree_commit, testrev.as_str());
+        assert_eq!(import.ref_updated, Some(false));
+        let rev = fixture.destrepo().require_rev("testref")?;
+        assert_eq!(rev.as_str(), testrev.as_str());
+
+        // Importing again into the same repo updates the existing ref
+        let mut imp =
+            store::ImageImporter::new(fixture.destrepo(), &srcoci_unverified, Default::default())
+                .await?;
+        imp.set_write_ref("testref");
+        let import = imp.unencapsulate().await.context("importing")?;
+        assert_eq!(import.ostree_commit, testrev.as_str());
+        assert_eq!(import.ref_updated, Some(true));
+        let rev = fixture.destrepo().require_rev("testref")?;
+        assert_eq!(rev.as_str(), testrev.as_str());
+    }
+
+    // Write a ref when the commit layer is already present
+    {
+        let fixture = Fixture::new_v1()?;
+        let mut imp =
+            store::ImageImporter::new(fixture.destrepo(), &srcoci_unverified, Default::default())
+                .await?;
+        // Keep the layer refs, but not the image ref; otherwise the image
+        // would be considered already present.
+        imp.set_no_imgref();
+        imp.disable_gc();
+        imp.set_write_ref("testref");
+        let prep = match imp.prepare().await.context("Init prep")? {
+            store::PrepareResult::AlreadyPresent(_) => panic!("should not be already imported"),
+            store::PrepareResult::Ready(r) => r,
+        };
+        // import() does not support writing a ref
+        assert_err_contains(
+            imp.import(prep).await,
+            "only supported when unencapsulating",
+        );
+
+        let mut imp =
+            store::ImageImporter::new(fixture.destrepo(), &srcoci_unverified, Default::default())
+                .await?;
+        imp.set_no_imgref();
+        imp.disable_gc();
+        let prep = match imp.prepare().await.context("Init prep")? {
+            store::PrepareResult::AlreadyPresent(_) => panic!("should not be already imported"),
+            store::PrepareResult::Ready(r) => r,
+        };
+        let _ = imp.import(prep).await.context("importing")?;
+        assert!(fixture.destrepo().resolve_rev("testref", true)?.is_none());
+
+        for expected_updated in [false, true] {
+            let mut imp = store::ImageImporter::new(
+                fixture.destrepo(),
+                &srcoci_unverified,
+                Default::default(),
+            )
+            .await?;
+            imp.set_write_ref("testref");
+            let import = imp.unencapsulate().await.context("importing")?;
+            assert_eq!(import.ostree_commit, testrev.as_str());
+            assert_eq!(import.ref_updated, Some(expected_updated));
+            assert_eq!(import.stats.n_layers_fetched, 0);
+            let rev = fixture.destrepo().require_rev("testref")?;
+            assert_eq!(rev.as_str(), testrev.as_str());
+        }
+    }
+
+    // Verify the image without importing it
+    {
+        let fixture = Fixture::new_v1()?;
+        let imp =
+            store::ImageImporter::new(fixture.destrepo(), &srcoci_unverified, Default::default())
+                .await?;
+        let verified = imp.verify().await.context("verifying")?;
+        assert_eq!(verified.manifest_digest, pushed_digest);
+        assert_eq!(verified.layers.len(), pushed_layers.len());
+        assert!(verified.layers.iter().all(|l| l.n_entries > 0));
+        assert!(verified.n_entries() > 0);
+        assert!(fixture
+            .destrepo()
+            .list_refs(None, gio::Cancellable::NONE)?
+            .is_empty());
+        assert!(!fixture.destrepo().has_object(
+            ostree::ObjectType::Commit,
+            testrev.as_str(),
+            gio::Cancellable::NONE
+        )?);
     }
 
     Ok(())